@@ -1,18 +1,73 @@
+use indicatif::{ProgressBar, ProgressStyle};
 use num::Complex;
+use rand::Rng;
 use rayon::prelude::*;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
 
-/// Try to determine if `c` is in the Mandelbrot set, using at most `limit`
-/// iterations to decide.
+/// The family of escape-time fractals this renderer knows how to compute.
+///
+/// Each kind shares the same `norm_sqr() > 4.0` bailout and iteration-count
+/// return; only the per-iteration update step (`step`) differs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FractalKind {
+    /// The classic recurrence, `z = z*z + c`.
+    Mandelbrot,
+    /// The cubic recurrence, `z = z*z*z + c`.
+    Multibrot3,
+    /// Take the absolute value of both components before squaring:
+    /// `z = (|z.re|, |z.im|)^2 + c`.
+    BurningShip,
+}
+
+impl FractalKind {
+    /// Apply this fractal's update step to `z`, given the constant `c`.
+    fn step(self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        match self {
+            FractalKind::Mandelbrot => z * z + c,
+            FractalKind::Multibrot3 => z * z * z + c,
+            FractalKind::BurningShip => {
+                let z = Complex { re: z.re.abs(), im: z.im.abs() };
+                z * z + c
+            }
+        }
+    }
+}
+
+impl FromStr for FractalKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mandelbrot" => Ok(FractalKind::Mandelbrot),
+            "multibrot3" => Ok(FractalKind::Multibrot3),
+            "burningship" => Ok(FractalKind::BurningShip),
+            _ => Err(format!("unknown fractal kind {:?}; expected one of \
+                               mandelbrot, multibrot3, burningship", s)),
+        }
+    }
+}
+
+#[test]
+fn test_fractal_kind_from_str() {
+    assert_eq!("mandelbrot".parse(), Ok(FractalKind::Mandelbrot));
+    assert_eq!("multibrot3".parse(), Ok(FractalKind::Multibrot3));
+    assert_eq!("burningship".parse(), Ok(FractalKind::BurningShip));
+    assert!("burning-ship".parse::<FractalKind>().is_err());
+}
+
+/// Try to determine if `c` is in the `kind` fractal's set, using at most
+/// `limit` iterations to decide.
 ///
 /// If `c` is not a member, return `Some(i)`, where `i` is the number of
 /// iterations it took for `c` to leave the circle of radius two centered on the
 /// origin. If `c` seems to be a member (more precisely, if we reached the
 /// iteration limit without being able to prove that `c` is not a member),
 /// return `None`.
-fn escape_time(c: Complex<f64>, limit: u32) -> Option<u32> {
+fn escape_time(kind: FractalKind, c: Complex<f64>, limit: u32) -> Option<u32> {
     let mut z = Complex { re: 0.0, im: 0.0 };
     for i in 0..limit {
-        z = z * z + c;
+        z = kind.step(z, c);
         if z.norm_sqr() > 4.0 {
             return Some(i);
         }
@@ -52,16 +107,18 @@ fn test_pixel_to_point() {
                Complex { re: -0.5, im: -0.5 });
 }
 
-/// Render a rectangle of the Mandelbrot set into a buffer of pixels.
+/// Render a rectangle of the `kind` fractal's set into a buffer of pixels.
 ///
 /// The `bounds` argument gives the width and height of the buffer `pixels`,
 /// which holds one grayscale pixel per byte. The `upper_left` and `lower_right`
 /// arguments specify points on the complex plane corresponding to the upper-
 /// left and lower-right corners of the pixel buffer.
-fn render(pixels: &mut [u8],
+fn render(kind: FractalKind,
+          pixels: &mut [u8],
           bounds: (usize, usize),
           upper_left: Complex<f64>,
-          lower_right: Complex<f64>)
+          lower_right: Complex<f64>,
+          limit: u32)
 {
     assert!(pixels.len() == bounds.0 * bounds.1);
 
@@ -70,7 +127,7 @@ fn render(pixels: &mut [u8],
             let point = pixel_to_point(bounds, (column, row),
                                        upper_left, lower_right);
             pixels[row * bounds.0 + column] =
-                match escape_time(point, 255) {
+                match escape_time(kind, point, limit) {
                     None => 0,
                     Some(count) => (count % (128*2)) as u8  //255 - count as u8
                 };
@@ -78,27 +135,444 @@ fn render(pixels: &mut [u8],
     }
 }
 
+/// Like `escape_time`, but for points that escape, also return the final
+/// `z` so the caller can compute a continuous (non-banded) iteration
+/// count. We run a few iterations past the bailout, since the smoothing
+/// formula in `smooth_mu` is more stable the further `z` has travelled
+/// past the radius-two circle.
+fn escape_time_smooth(kind: FractalKind, c: Complex<f64>, limit: u32) -> Option<(u32, Complex<f64>)> {
+    const EXTRA_ITERATIONS: u32 = 3;
+
+    let mut z = Complex { re: 0.0, im: 0.0 };
+    for i in 0..limit {
+        z = kind.step(z, c);
+        if z.norm_sqr() > 4.0 {
+            for _ in 0..EXTRA_ITERATIONS {
+                z = kind.step(z, c);
+            }
+            return Some((i, z));
+        }
+    }
+
+    None
+}
+
+/// Turn a raw iteration count and the orbit's final `z` into a continuous
+/// (fractional) iteration count, so that colors can be interpolated
+/// smoothly between bands instead of banding at integer counts.
+fn smooth_mu(count: u32, z: Complex<f64>) -> f64 {
+    count as f64 + 1.0 - (z.norm().ln().ln() / 2.0_f64.ln())
+}
+
+/// One color stop in a piecewise-linear gradient: `t` is the position
+/// along the gradient in `[0, 1)`, and `color` is the `(r, g, b)` value at
+/// that position.
+struct ColorStop {
+    t: f64,
+    color: (u8, u8, u8),
+}
+
+/// The classic "Ultra Fractal" blue/white/orange palette, cycling back to
+/// its first stop at `t = 1.0`.
+const ULTRA_FRACTAL_PALETTE: [ColorStop; 5] = [
+    ColorStop { t: 0.0,    color: (0, 7, 100) },
+    ColorStop { t: 0.16,   color: (32, 107, 203) },
+    ColorStop { t: 0.42,   color: (237, 255, 255) },
+    ColorStop { t: 0.6425, color: (255, 170, 0) },
+    ColorStop { t: 0.8575, color: (0, 2, 0) },
+];
+
+/// Map a fractional iteration count `mu` to an `(r, g, b)` color by
+/// cycling it into `[0, 1)` and interpolating linearly between the
+/// palette's stops.
+fn palette_color(mu: f64) -> (u8, u8, u8) {
+    // The palette repeats every few iterations; this period is what gives
+    // the gradient its visible bands of color rather than a single fade.
+    let t = (mu / 20.0).rem_euclid(1.0);
+
+    let stops = &ULTRA_FRACTAL_PALETTE;
+    let mut lower = stops.last().unwrap();
+    let mut upper = &stops[0];
+    for window in stops.windows(2) {
+        if t >= window[0].t && t < window[1].t {
+            lower = &window[0];
+            upper = &window[1];
+            break;
+        }
+    }
+
+    let span = if upper.t > lower.t { upper.t - lower.t } else { 1.0 - lower.t + upper.t };
+    let offset = if t >= lower.t { t - lower.t } else { t + 1.0 - lower.t };
+    let fraction = if span == 0.0 { 0.0 } else { offset / span };
+
+    let lerp = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * fraction) as u8;
+    (lerp(lower.color.0, upper.color.0),
+     lerp(lower.color.1, upper.color.1),
+     lerp(lower.color.2, upper.color.2))
+}
+
+/// Render a rectangle of the `kind` fractal's set into `pixels` using
+/// smooth, continuous coloring instead of `render`'s banded grayscale.
+///
+/// `pixels` must hold three bytes (red, green, blue) per pixel. Interior
+/// points (those that never escape within `limit` iterations) are colored
+/// black.
+fn render_smooth(kind: FractalKind,
+                  pixels: &mut [u8],
+                  bounds: (usize, usize),
+                  upper_left: Complex<f64>,
+                  lower_right: Complex<f64>,
+                  limit: u32)
+{
+    assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+
+    for row in 0 .. bounds.1 {
+        for column in 0 .. bounds.0 {
+            let point = pixel_to_point(bounds, (column, row), upper_left, lower_right);
+            let (r, g, b) = match escape_time_smooth(kind, point, limit) {
+                None => (0, 0, 0),
+                Some((count, z)) => palette_color(smooth_mu(count, z)),
+            };
+            let i = (row * bounds.0 + column) * 3;
+            pixels[i] = r;
+            pixels[i + 1] = g;
+            pixels[i + 2] = b;
+        }
+    }
+}
+
+/// The inverse of `pixel_to_point`: given a point on the complex plane,
+/// return the pixel that contains it, or `None` if the point falls outside
+/// the `bounds` rectangle.
+fn point_to_pixel(bounds: (usize, usize),
+                   point: Complex<f64>,
+                   upper_left: Complex<f64>,
+                   lower_right: Complex<f64>)
+    -> Option<(usize, usize)>
+{
+    let width = lower_right.re - upper_left.re;
+    let height = upper_left.im - lower_right.im;
+
+    let column = ((point.re - upper_left.re) / width * bounds.0 as f64).floor() as isize;
+    let row = ((upper_left.im - point.im) / height * bounds.1 as f64).floor() as isize;
+
+    if column < 0 || row < 0 || column as usize >= bounds.0 || row as usize >= bounds.1 {
+        None
+    } else {
+        Some((column as usize, row as usize))
+    }
+}
+
+/// Replay the orbit of `c` under the `kind` fractal's update step. If the
+/// orbit escapes the circle of radius two within `limit` iterations,
+/// increment the hit counter of every pixel the trajectory passed through.
+/// Orbits that never escape (points inside the set) contribute nothing,
+/// since we only walk the recorded trajectory once we already know it
+/// escaped.
+fn accumulate_orbit(kind: FractalKind,
+                     c: Complex<f64>,
+                     limit: u32,
+                     bounds: (usize, usize),
+                     upper_left: Complex<f64>,
+                     lower_right: Complex<f64>,
+                     counts: &[AtomicU32])
+{
+    let mut z = Complex { re: 0.0, im: 0.0 };
+    let mut orbit = Vec::with_capacity(limit as usize);
+    let mut escaped = false;
+
+    for _ in 0..limit {
+        z = kind.step(z, c);
+        orbit.push(z);
+        if z.norm_sqr() > 4.0 {
+            escaped = true;
+            break;
+        }
+    }
+
+    if !escaped {
+        return;
+    }
+
+    for point in orbit {
+        if let Some((column, row)) = point_to_pixel(bounds, point, upper_left, lower_right) {
+            counts[row * bounds.0 + column].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Gamma-correct a raw hit count into a displayable grayscale byte, scaling
+/// by the largest count seen anywhere in the grid.
+fn normalize_hits(hits: u32, max: u32) -> u8 {
+    if max == 0 {
+        return 0;
+    }
+    let fraction = hits as f64 / max as f64;
+    (fraction.powf(1.0 / 2.2) * 255.0) as u8
+}
+
+/// Render a Buddhabrot into `pixels`, one grayscale byte per pixel.
+///
+/// Unlike `render`, which colors each pixel by its own escape time, the
+/// Buddhabrot draws `samples` random points `c` from the `upper_left` /
+/// `lower_right` rectangle, runs the `kind` fractal's update step up to
+/// `limit` iterations, and
+/// for every orbit that escapes, accumulates a hit in every pixel the orbit
+/// passed through. The resulting hit grid is normalized into the output
+/// buffer. Each sample is independent, so rayon can process them
+/// concurrently against a shared grid of `AtomicU32` hit counters.
+///
+/// If `progress` is given, its counter is incremented once per sample as
+/// that sample's orbit finishes, from whichever thread happened to draw
+/// it; `ProgressBar::inc` is backed by an atomic counter, so this is safe
+/// to call concurrently without any extra synchronization.
+#[allow(clippy::too_many_arguments)]
+fn render_buddhabrot(kind: FractalKind,
+                      pixels: &mut [u8],
+                      bounds: (usize, usize),
+                      upper_left: Complex<f64>,
+                      lower_right: Complex<f64>,
+                      samples: usize,
+                      limit: u32,
+                      progress: Option<&ProgressBar>)
+{
+    assert!(pixels.len() == bounds.0 * bounds.1);
+
+    let counts: Vec<AtomicU32> = (0 .. bounds.0 * bounds.1)
+        .map(|_| AtomicU32::new(0))
+        .collect();
+
+    (0 .. samples).into_par_iter().for_each(|_| {
+        let mut rng = rand::thread_rng();
+        let c = Complex {
+            re: rng.gen_range(upper_left.re, lower_right.re),
+            im: rng.gen_range(lower_right.im, upper_left.im),
+        };
+        accumulate_orbit(kind, c, limit, bounds, upper_left, lower_right, &counts);
+        if let Some(pb) = progress {
+            pb.inc(1);
+        }
+    });
+
+    let max = counts.iter().map(|c| c.load(Ordering::Relaxed)).max().unwrap_or(0);
+    for (pixel, count) in pixels.iter_mut().zip(counts.iter()) {
+        *pixel = normalize_hits(count.load(Ordering::Relaxed), max);
+    }
+}
+
+/// Render a "Nebulabrot": a Buddhabrot variant that runs three passes with
+/// different iteration limits and writes each pass into its own RGB
+/// channel, so that points which only escape slowly tint the image
+/// differently from points that escape almost immediately.
+///
+/// `pixels` must hold three bytes (red, green, blue) per pixel. `progress`,
+/// if given, is shared across all three passes, so its length should be
+/// `samples * 3`.
+#[allow(clippy::too_many_arguments)]
+fn render_nebulabrot(kind: FractalKind,
+                      pixels: &mut [u8],
+                      bounds: (usize, usize),
+                      upper_left: Complex<f64>,
+                      lower_right: Complex<f64>,
+                      samples: usize,
+                      limits: (u32, u32, u32),
+                      progress: Option<&ProgressBar>)
+{
+    assert!(pixels.len() == bounds.0 * bounds.1 * 3);
+
+    let mut channel = vec![0u8; bounds.0 * bounds.1];
+    for (band, &limit) in [limits.0, limits.1, limits.2].iter().enumerate() {
+        render_buddhabrot(kind, &mut channel, bounds, upper_left, lower_right, samples, limit, progress);
+        for (i, &value) in channel.iter().enumerate() {
+            pixels[i * 3 + band] = value;
+        }
+    }
+}
+
+/// The base-83 alphabet used by the BlurHash format.
+const BASE83_DIGITS: &[u8; 83] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Encode `value` as a base-83 string of exactly `length` digits,
+/// most-significant digit first.
+fn encode_base83(value: u32, length: usize) -> String {
+    (0..length)
+        .map(|i| {
+            let shift = (length - 1 - i) as u32;
+            let digit = (value / 83u32.pow(shift)) % 83;
+            BASE83_DIGITS[digit as usize] as char
+        })
+        .collect()
+}
+
+/// Convert an 8-bit sRGB channel value into linear light.
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Convert a linear-light channel value back into an 8-bit sRGB value.
+fn linear_to_srgb(value: f64) -> u32 {
+    let v = value.clamp(0.0, 1.0);
+    let encoded = if v <= 0.0031308 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+    (encoded * 255.0).round() as u32
+}
+
+fn sign_pow(value: f64, exponent: f64) -> f64 {
+    value.signum() * value.abs().powf(exponent)
+}
+
+/// Compute the `(r, g, b)` basis factor for component `(i, j)` across the
+/// whole image, in linear light. `channels` is 1 for a grayscale buffer or
+/// 3 for an RGB buffer.
+fn basis_factor(pixels: &[u8], channels: usize, bounds: (usize, usize), i: u32, j: u32) -> (f64, f64, f64) {
+    let (width, height) = bounds;
+    let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+    let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+
+    for y in 0 .. height {
+        for x in 0 .. width {
+            let basis = (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                      * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+            let idx = (y * width + x) * channels;
+            if channels == 1 {
+                let lin = srgb_to_linear(pixels[idx]);
+                r += basis * lin;
+                g += basis * lin;
+                b += basis * lin;
+            } else {
+                r += basis * srgb_to_linear(pixels[idx]);
+                g += basis * srgb_to_linear(pixels[idx + 1]);
+                b += basis * srgb_to_linear(pixels[idx + 2]);
+            }
+        }
+    }
+
+    let scale = normalisation / (width * height) as f64;
+    (r * scale, g * scale, b * scale)
+}
+
+/// Quantize one AC channel value into BlurHash's 19-level range (`0..=18`).
+fn quantize_ac_component(value: f64) -> u32 {
+    (value * 9.0 + 9.5).floor().clamp(0.0, 18.0) as u32
+}
+
+/// Encode `pixels` as a BlurHash string using `components_x` by
+/// `components_y` basis functions (valid range `1..=9` each).
+///
+/// `pixels` holds either one grayscale byte or three RGB bytes per pixel;
+/// which one it is is inferred from its length against `bounds`. Each
+/// channel is first converted from sRGB to linear light, then projected
+/// onto `components_x * components_y` 2D cosine basis functions (the same
+/// idea as a DCT); the DC term `(0, 0)` is the image's average color, and
+/// the remaining AC terms are quantized and packed into a compact
+/// base-83 string.
+fn encode_blurhash(pixels: &[u8], bounds: (usize, usize), components_x: u32, components_y: u32) -> String {
+    assert!((1..=9).contains(&components_x));
+    assert!((1..=9).contains(&components_y));
+
+    let channels = pixels.len() / (bounds.0 * bounds.1);
+    assert!(channels == 1 || channels == 3);
+
+    let mut factors = Vec::with_capacity((components_x * components_y) as usize);
+    for j in 0 .. components_y {
+        for i in 0 .. components_x {
+            factors.push(basis_factor(pixels, channels, bounds, i, j));
+        }
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let mut hash = String::new();
+    let size_flag = (components_x - 1) + (components_y - 1) * 9;
+    hash.push_str(&encode_base83(size_flag, 1));
+
+    let maximum_value = if !ac.is_empty() {
+        let actual_maximum = ac.iter()
+            .flat_map(|&(r, g, b)| vec![r.abs(), g.abs(), b.abs()])
+            .fold(0.0_f64, f64::max);
+        let quantised = (actual_maximum * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32;
+        hash.push_str(&encode_base83(quantised, 1));
+        (quantised as f64 + 1.0) / 166.0
+    } else {
+        hash.push_str(&encode_base83(0, 1));
+        1.0
+    };
+
+    let dc_value = (linear_to_srgb(dc.0) << 16) + (linear_to_srgb(dc.1) << 8) + linear_to_srgb(dc.2);
+    hash.push_str(&encode_base83(dc_value, 4));
+
+    for &(r, g, b) in ac {
+        let quant_r = quantize_ac_component(sign_pow(r / maximum_value, 0.5));
+        let quant_g = quantize_ac_component(sign_pow(g / maximum_value, 0.5));
+        let quant_b = quantize_ac_component(sign_pow(b / maximum_value, 0.5));
+        let value = quant_r * 19 * 19 + quant_g * 19 + quant_b;
+        hash.push_str(&encode_base83(value, 2));
+    }
+
+    hash
+}
+
+#[test]
+fn test_encode_base83() {
+    assert_eq!(encode_base83(0, 1), "0");
+    assert_eq!(encode_base83(82, 1), "~");
+    assert_eq!(encode_base83(0, 4), "0000");
+    // 83 overflows a single digit, so it carries into the next one.
+    assert_eq!(encode_base83(83, 2), "10");
+}
+
+#[test]
+fn test_srgb_linear_round_trip() {
+    assert_eq!(linear_to_srgb(srgb_to_linear(0)), 0);
+    assert_eq!(linear_to_srgb(srgb_to_linear(255)), 255);
+    for value in 0..=255u8 {
+        let round_tripped = linear_to_srgb(srgb_to_linear(value));
+        assert!((round_tripped as i32 - value as i32).abs() <= 1,
+                "sRGB round trip drifted too far for {}: got {}", value, round_tripped);
+    }
+}
+
+#[test]
+fn test_basis_factor_dc_is_average_color() {
+    // A uniform 2x2 gray image: the DC term (0, 0) basis is constant 1
+    // everywhere, so it should reproduce the image's (linear) average
+    // color exactly, with all three channels equal.
+    let pixels = [128u8; 4];
+    let (r, g, b) = basis_factor(&pixels, 1, (2, 2), 0, 0);
+    let expected = srgb_to_linear(128);
+    assert!((r - expected).abs() < 1e-9);
+    assert_eq!(r, g);
+    assert_eq!(g, b);
+}
+
 use image::ColorType;
 use image::png::PNGEncoder;
 use std::fs::File;
 
 /// Write the buffer `pixels`, whose dimensions are given by `bounds`, to the
-/// file named `filename`.
-fn write_image(filename: String, pixels: &[u8], bounds: (usize, usize))
+/// file named `filename`, encoded as `color`.
+fn write_image(filename: String, pixels: &[u8], bounds: (usize, usize), color: ColorType)
     -> Result<(), std::io::Error>
 {
     let output = File::create(filename)?;
 
     let encoder = PNGEncoder::new(output);
-    encoder.encode(&pixels,
+    encoder.encode(pixels,
                    bounds.0 as u32, bounds.1 as u32,
-                   ColorType::Gray(8))?;
+                   color)?;
 
     Ok(())
 }
 
 
-fn generate_field(size: (usize, usize), pixels: &mut[u8], upper_left: Complex<f64>, lower_right: Complex<f64>) {
+/// Render one field, splitting the work into horizontal bands across
+/// rayon's thread pool. If `progress` is given, its counter is incremented
+/// once per band as that band finishes, from whichever thread happened to
+/// render it; `ProgressBar::inc` is backed by an atomic counter, so this is
+/// safe to call concurrently without any extra synchronization.
+fn generate_field(kind: FractalKind, limit: u32, size: (usize, usize), pixels: &mut[u8], upper_left: Complex<f64>, lower_right: Complex<f64>, progress: Option<&ProgressBar>) {
 
     // Scope of slicing up `pixels` into horizontal bands.
     {
@@ -115,40 +589,296 @@ fn generate_field(size: (usize, usize), pixels: &mut[u8], upper_left: Complex<f6
                                                      upper_left, lower_right);
                 let band_lower_right = pixel_to_point(size, (size.0, top + 1),
                                                       upper_left, lower_right);
-                render(band, band_bounds, band_upper_left, band_lower_right);
+                render(kind, band, band_bounds, band_upper_left, band_lower_right, limit);
+                if let Some(pb) = progress {
+                    pb.inc(1);
+                }
             });
     }
-    
+
+}
+
+/// Like `generate_field`, but renders with `render_smooth` into an RGB
+/// buffer, splitting the work across rayon the same way: one horizontal
+/// band of pixels (three bytes wide) per parallel task.
+fn generate_field_smooth(kind: FractalKind, limit: u32, size: (usize, usize), pixels: &mut [u8], upper_left: Complex<f64>, lower_right: Complex<f64>, progress: Option<&ProgressBar>) {
+    let bands: Vec<(usize, &mut [u8])> = pixels
+        .chunks_mut(size.0 * 3)
+        .enumerate()
+        .collect();
+    bands.into_par_iter()
+        .weight_max()
+        .for_each(|(i, band)| {
+            let top = i;
+            let band_bounds = (size.0, 1);
+            let band_upper_left = pixel_to_point(size, (0, top),
+                                                 upper_left, lower_right);
+            let band_lower_right = pixel_to_point(size, (size.0, top + 1),
+                                                  upper_left, lower_right);
+            render_smooth(kind, band, band_bounds, band_upper_left, band_lower_right, limit);
+            if let Some(pb) = progress {
+                pb.inc(1);
+            }
+        });
+}
+
+/// Parse the string `s` as a coordinate pair, like `"400x600"` or
+/// `"1.0,0.5"`. `separator` must be the character that separates the two
+/// halves. If `s` has the proper form, return `Some((x, y))`; otherwise,
+/// return `None`.
+fn parse_pair<T: FromStr>(s: &str, separator: char) -> Option<(T, T)> {
+    match s.find(separator) {
+        None => None,
+        Some(index) => {
+            match (T::from_str(&s[..index]), T::from_str(&s[index + 1..])) {
+                (Ok(l), Ok(r)) => Some((l, r)),
+                _ => None
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_pair() {
+    assert_eq!(parse_pair::<i32>("", ','), None);
+    assert_eq!(parse_pair::<i32>("10,", ','), None);
+    assert_eq!(parse_pair::<i32>(",10", ','), None);
+    assert_eq!(parse_pair::<i32>("10,20", ','), Some((10, 20)));
+    assert_eq!(parse_pair::<i32>("10,20xy", ','), None);
+    assert_eq!(parse_pair::<f64>("0.5x", 'x'), None);
+    assert_eq!(parse_pair::<f64>("0.5x1.5", 'x'), Some((0.5, 1.5)));
+}
+
+/// Parse a pair of floating-point numbers separated by a comma as a
+/// complex number.
+fn parse_complex(s: &str) -> Option<Complex<f64>> {
+    parse_pair(s, ',').map(|(re, im)| Complex { re, im })
+}
+
+#[test]
+fn test_parse_complex() {
+    assert_eq!(parse_complex("1.25,-0.0625"),
+               Some(Complex { re: 1.25, im: -0.0625 }));
+    assert_eq!(parse_complex(",-0.0625"), None);
+}
+
+/// Which rendering pipeline `main` should run for each tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderMode {
+    /// The classic per-pixel escape-time render (`render`/`generate_field`).
+    Classic,
+    /// Smooth, continuously-colored escape-time render (`render_smooth`).
+    Smooth,
+    /// Buddhabrot orbit accumulation (`render_buddhabrot`).
+    Buddhabrot,
+    /// Three-pass Buddhabrot into an RGB image (`render_nebulabrot`).
+    Nebulabrot,
+}
+
+impl FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(RenderMode::Classic),
+            "smooth" => Ok(RenderMode::Smooth),
+            "buddhabrot" => Ok(RenderMode::Buddhabrot),
+            "nebulabrot" => Ok(RenderMode::Nebulabrot),
+            _ => Err(format!("unknown render mode {:?}; expected one of \
+                               classic, smooth, buddhabrot, nebulabrot", s)),
+        }
+    }
+}
+
+/// The three iteration limits the "Nebulabrot" variant runs into its red,
+/// green, and blue channels respectively.
+const NEBULABROT_LIMITS: (u32, u32, u32) = (50, 500, 5000);
+
+/// Default orbit sample count for `--mode buddhabrot`/`--mode nebulabrot`.
+const DEFAULT_SAMPLES: usize = 1_000_000;
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} [--force] [--quiet] [--mode=classic|smooth|buddhabrot|nebulabrot] \
+               [--kind=mandelbrot|multibrot3|burningship] [--samples=N] \
+               FILE PIXELS UPPERLEFT LOWERRIGHT LIMIT TILES", program);
+    eprintln!("Example: {} field 6400x6400 -1.16,0.29 -1.14,0.275 255 10", program);
+    eprintln!("  --force         re-render tiles even if their PNG already exists on disk");
+    eprintln!("  --quiet         suppress the progress bars");
+    eprintln!("  --mode=MODE     classic (default), smooth, buddhabrot, or nebulabrot");
+    eprintln!("  --kind=KIND     mandelbrot (default), multibrot3, or burningship");
+    eprintln!("  --samples=N     orbit sample count for buddhabrot/nebulabrot modes");
 }
 
 fn main() {
+    let raw_args: Vec<String> = std::env::args().collect();
+    let program = raw_args[0].clone();
+
+    let mut force = false;
+    let mut quiet = false;
+    let mut mode = RenderMode::Classic;
+    let mut kind = FractalKind::Mandelbrot;
+    let mut samples = DEFAULT_SAMPLES;
+    let mut args = vec![program.clone()];
+    for arg in raw_args.into_iter().skip(1) {
+        if let Some(value) = arg.strip_prefix("--mode=") {
+            mode = value.parse().unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                print_usage(&program);
+                std::process::exit(1);
+            });
+        } else if let Some(value) = arg.strip_prefix("--kind=") {
+            kind = value.parse().unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                print_usage(&program);
+                std::process::exit(1);
+            });
+        } else if let Some(value) = arg.strip_prefix("--samples=") {
+            samples = value.parse().unwrap_or_else(|_| {
+                eprintln!("error: invalid sample count {:?}", value);
+                print_usage(&program);
+                std::process::exit(1);
+            });
+        } else {
+            match arg.as_str() {
+                "--force" => force = true,
+                "--quiet" => quiet = true,
+                _ => args.push(arg),
+            }
+        }
+    }
+
+    if args.len() != 7 {
+        print_usage(&program);
+        std::process::exit(1);
+    }
+
+    let filename = &args[1];
+
+    let field_size = parse_pair(&args[2], 'x')
+        .unwrap_or_else(|| {
+            eprintln!("error: invalid pixel bounds {:?}", args[2]);
+            print_usage(&program);
+            std::process::exit(1);
+        });
+
+    let mf_upper_left = parse_complex(&args[3])
+        .unwrap_or_else(|| {
+            eprintln!("error: invalid upper-left corner point {:?}", args[3]);
+            print_usage(&program);
+            std::process::exit(1);
+        });
+
+    let mf_lower_right = parse_complex(&args[4])
+        .unwrap_or_else(|| {
+            eprintln!("error: invalid lower-right corner point {:?}", args[4]);
+            print_usage(&program);
+            std::process::exit(1);
+        });
+
+    if mf_upper_left.re >= mf_lower_right.re || mf_lower_right.im >= mf_upper_left.im {
+        eprintln!("error: upper-left/lower-right corners describe a zero-width \
+                    or inverted viewport ({:?}, {:?})", mf_upper_left, mf_lower_right);
+        print_usage(&program);
+        std::process::exit(1);
+    }
+
+    let limit: u32 = args[5].parse().unwrap_or_else(|_| {
+        eprintln!("error: invalid iteration limit {:?}", args[5]);
+        print_usage(&program);
+        std::process::exit(1);
+    });
 
-    let field_size = (6400, 6400);
-    let mf_square_fields:usize = 10;
+    let mf_square_fields: usize = args[6].parse().unwrap_or_else(|_| {
+        eprintln!("error: invalid tile count {:?}", args[6]);
+        print_usage(&program);
+        std::process::exit(1);
+    });
 
-    let mf_upper_left: Complex<f64> = Complex {re: -1.16, im: 0.29};
-    let mf_lower_right: Complex<f64> = Complex {re: -1.14, im: 0.275};
     let mf_size = Complex{re: mf_upper_left.re - mf_lower_right.re, im: mf_upper_left.im - mf_lower_right.im};
     let field_size_complex = Complex{re: mf_size.re / mf_square_fields as f64, im: mf_size.im / mf_square_fields as f64};
 
-    let mut pixels = vec![0; field_size.0 * field_size.1];
+    let channels = match mode {
+        RenderMode::Smooth | RenderMode::Nebulabrot => 3,
+        RenderMode::Classic | RenderMode::Buddhabrot => 1,
+    };
+    let color_type = if channels == 3 { ColorType::RGB(8) } else { ColorType::Gray(8) };
+    let mut pixels = vec![0u8; field_size.0 * field_size.1 * channels];
 
+    let tiles_total = (mf_square_fields * mf_square_fields) as u64;
+    let tiles_progress = if quiet { ProgressBar::hidden() } else { ProgressBar::new(tiles_total) };
+    tiles_progress.set_style(
+        ProgressStyle::default_bar()
+            .template("tiles [{bar:40}] {pos}/{len} {msg}")
+            .expect("invalid progress bar template"));
 
     for row in 0..mf_square_fields {
         for col in 0..mf_square_fields {
+            let tile_filename = format!("{}_{:03}_{:03}_0.png", filename, row, col);
+
+            if !force && std::path::Path::new(&tile_filename).exists() {
+                tiles_progress.set_message(format!("skipping {} (already rendered)", tile_filename));
+                tiles_progress.inc(1);
+                continue;
+            }
+
             let re_offset = mf_size.re / mf_square_fields as f64 * col as f64;
             let im_offset = mf_size.im / mf_square_fields as f64 * row as f64;
             let field_upper_left = mf_upper_left - Complex{re: re_offset, im: im_offset};
             let field_lower_right = field_upper_left - field_size_complex;
 
-            println!("generate {}_{} {:?} {:?}", row, col, field_upper_left, field_lower_right);
+            tiles_progress.set_message(format!("rendering {}", tile_filename));
+
+            match mode {
+                RenderMode::Classic => {
+                    let band_progress = if quiet { ProgressBar::hidden() } else { ProgressBar::new(field_size.1 as u64) };
+                    band_progress.set_style(
+                        ProgressStyle::default_bar()
+                            .template("  bands [{bar:40}] {pos}/{len}")
+                            .expect("invalid progress bar template"));
+
+                    generate_field(kind, limit, field_size, &mut pixels, field_upper_left, field_lower_right, Some(&band_progress));
+                    band_progress.finish_and_clear();
+                }
+                RenderMode::Smooth => {
+                    let band_progress = if quiet { ProgressBar::hidden() } else { ProgressBar::new(field_size.1 as u64) };
+                    band_progress.set_style(
+                        ProgressStyle::default_bar()
+                            .template("  bands [{bar:40}] {pos}/{len}")
+                            .expect("invalid progress bar template"));
+
+                    generate_field_smooth(kind, limit, field_size, &mut pixels, field_upper_left, field_lower_right, Some(&band_progress));
+                    band_progress.finish_and_clear();
+                }
+                RenderMode::Buddhabrot => {
+                    let sample_progress = if quiet { ProgressBar::hidden() } else { ProgressBar::new(samples as u64) };
+                    sample_progress.set_style(
+                        ProgressStyle::default_bar()
+                            .template("  samples [{bar:40}] {pos}/{len}")
+                            .expect("invalid progress bar template"));
+
+                    render_buddhabrot(kind, &mut pixels, field_size, field_upper_left, field_lower_right, samples, limit, Some(&sample_progress));
+                    sample_progress.finish_and_clear();
+                }
+                RenderMode::Nebulabrot => {
+                    let sample_progress = if quiet { ProgressBar::hidden() } else { ProgressBar::new(samples as u64 * 3) };
+                    sample_progress.set_style(
+                        ProgressStyle::default_bar()
+                            .template("  samples [{bar:40}] {pos}/{len}")
+                            .expect("invalid progress bar template"));
+
+                    render_nebulabrot(kind, &mut pixels, field_size, field_upper_left, field_lower_right, samples, NEBULABROT_LIMITS, Some(&sample_progress));
+                    sample_progress.finish_and_clear();
+                }
+            }
+
+            write_image(tile_filename.clone(), &pixels, field_size, color_type).expect("error writing PNG file");
+
+            let hash = encode_blurhash(&pixels, field_size, 4, 3);
+            let hash_filename = format!("{}_{:03}_{:03}_0.txt", filename, row, col);
+            std::fs::write(&hash_filename, &hash).expect("error writing BlurHash sidecar file");
 
-            generate_field(field_size, &mut pixels, field_upper_left, field_lower_right);
-            
-            let filename = format!("field_{:03}_{:03}_0.png", row, col);
-            println!("Go write {}", filename);
-            write_image(filename, &pixels, field_size).expect("error writing PNG file");
-            println!("Write done");
+            tiles_progress.inc(1);
         }
     }
+    tiles_progress.finish_with_message("done");
 }